@@ -0,0 +1,100 @@
+use anyhow::{anyhow, Result};
+use cargo::core::{PackageSet, Resolve};
+
+use crate::license::{SpdxExpr, CLOSED_LICENSE};
+
+/// A dependency whose license expression doesn't satisfy the configured
+/// allow/deny policy
+struct Violation {
+    name: String,
+    version: String,
+    license: String,
+}
+
+/// Walks every non-path dependency in `resolve` and checks its license
+/// expression against `allowed`/`denied` SPDX identifier lists.
+///
+/// Compliance respects the expression's `AND`/`OR` structure (see
+/// [`SpdxExpr::is_compliant`]) rather than flattening it to a set, so a
+/// conjunctive dual-license like `Apache-2.0 AND GPL-3.0` is only compliant
+/// when every branch is, while a disjunctive one like `MIT OR GPL-2.0` only
+/// needs a single fully-compliant branch. On any violation this returns an
+/// error listing every offending crate, version and license so the recipe
+/// isn't generated with a non-compliant dependency.
+pub(crate) fn check(
+    package_set: &PackageSet,
+    resolve: &Resolve,
+    allowed: &[String],
+    denied: &[String],
+) -> Result<()> {
+    let mut violations = vec![];
+
+    for pkg_id in resolve.iter() {
+        if pkg_id.source_id().is_path() {
+            // our own workspace members aren't third-party dependencies
+            continue;
+        }
+
+        let pkg = package_set.get_one(pkg_id)?;
+        let metadata = pkg.manifest().metadata();
+        let license_str = metadata
+            .license
+            .clone()
+            .or_else(|| metadata.license_file.clone())
+            .unwrap_or_else(|| CLOSED_LICENSE.to_string());
+
+        if violates_policy(&license_str, allowed, denied)? {
+            violations.push(Violation {
+                name: pkg_id.name().to_string(),
+                version: pkg_id.version().to_string(),
+                license: license_str,
+            });
+        }
+    }
+
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    let mut report = String::from("the following dependencies violate the license policy:\n");
+    for v in &violations {
+        report.push_str(&format!("  {} {} ({})\n", v.name, v.version, v.license));
+    }
+
+    Err(anyhow!(report))
+}
+
+/// Parses `license_str` as an SPDX expression and checks it against the
+/// `allowed`/`denied` policy, honoring `AND`/`OR` structure
+fn violates_policy(license_str: &str, allowed: &[String], denied: &[String]) -> Result<bool> {
+    let expr = SpdxExpr::parse(license_str)?;
+    Ok(!expr.is_compliant(allowed, denied))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn and_license_needs_every_branch_allowed() {
+        let allowed = vec!["Apache-2.0".to_string()];
+        assert!(violates_policy("Apache-2.0 AND GPL-3.0", &allowed, &[]).unwrap());
+
+        let allowed = vec!["Apache-2.0".to_string(), "GPL-3.0".to_string()];
+        assert!(!violates_policy("Apache-2.0 AND GPL-3.0", &allowed, &[]).unwrap());
+    }
+
+    #[test]
+    fn or_license_passes_with_one_compliant_branch() {
+        let denied = vec!["GPL-2.0".to_string()];
+        assert!(!violates_policy("MIT OR GPL-2.0", &[], &denied).unwrap());
+
+        let denied = vec!["MIT".to_string(), "GPL-2.0".to_string()];
+        assert!(violates_policy("MIT OR GPL-2.0", &[], &denied).unwrap());
+    }
+
+    #[test]
+    fn empty_allow_list_means_anything_goes() {
+        assert!(!violates_policy("MIT", &[], &[]).unwrap());
+    }
+}
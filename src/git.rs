@@ -0,0 +1,83 @@
+/*
+ * Copyright 2016-2017 Doug Goldstein <cardoe@cardoe.com>
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+use anyhow::Result;
+use cargo::GlobalContext;
+
+/// Which flavor of `git://` fetcher prefix BitBake should use for a
+/// dependency's `SRC_URI` entry
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) enum GitPrefix {
+    /// plain `git://` with `protocol=https` appended, the common case
+    #[default]
+    Plain,
+}
+
+/// Turns a cargo git source URL into the `git://...;protocol=https;...`
+/// form BitBake's fetcher expects, naming the destination directory after
+/// `name` so `EXTRA_OECARGO_PATHS` can find it.
+pub(crate) fn git_to_yocto_git_url(url: &str, name: Option<&str>, _prefix: GitPrefix) -> String {
+    // cargo hands us a plain https/ssh URL; BitBake's git fetcher wants the
+    // scheme replaced with `git` and the transport kept as a parameter
+    let (proto, rest) = url.split_once("://").unwrap_or(("https", url));
+
+    let mut yocto_url = format!("git://{rest};protocol={proto}");
+    if let Some(name) = name {
+        yocto_url.push_str(&format!(";destsuffix={name}"));
+    }
+    yocto_url.push_str(";nobranch=1");
+    yocto_url
+}
+
+/// Information about the git repository the recipe itself is being
+/// generated from, used to fill in `SRCREV`/`PV` for non-tag checkouts
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ProjectRepo {
+    /// URI BitBake should fetch the project's own sources from
+    pub(crate) uri: String,
+    /// the revision currently checked out
+    pub(crate) rev: String,
+    /// whether `rev` corresponds exactly to a tag
+    pub(crate) tag: bool,
+}
+
+impl ProjectRepo {
+    /// Inspects the git repository containing the current working
+    /// directory and reports its remote URI and checked out revision
+    pub(crate) fn new(gctx: &GlobalContext) -> Result<ProjectRepo> {
+        let repo = git2::Repository::discover(gctx.cwd())?;
+        let head = repo.head()?;
+        let commit = head.peel_to_commit()?;
+        let rev = commit.id().to_string();
+
+        let uri = repo
+            .find_remote("origin")
+            .ok()
+            .and_then(|remote| {
+                remote
+                    .url()
+                    .map(|url| git_to_yocto_git_url(url, None, GitPrefix::default()))
+            })
+            .unwrap_or_default();
+
+        let tag = repo
+            .tag_names(None)?
+            .iter()
+            .flatten()
+            .any(|tag_name| {
+                repo.revparse_single(tag_name)
+                    .and_then(|obj| obj.peel_to_commit())
+                    .map(|tag_commit| tag_commit.id() == commit.id())
+                    .unwrap_or(false)
+            });
+
+        Ok(ProjectRepo { uri, rev, tag })
+    }
+}
@@ -0,0 +1,347 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+/// License to assume when a crate provides no license metadata at all
+pub(crate) const CLOSED_LICENSE: &str = "CLOSED";
+
+/// Well known license file names we look for alongside `Cargo.toml`,
+/// in order of preference
+const LICENSE_FILE_CANDIDATES: &[&str] = &[
+    "LICENSE",
+    "LICENSE.md",
+    "LICENSE.txt",
+    "LICENSE-MIT",
+    "LICENSE-APACHE",
+    "COPYING",
+];
+
+/// Generates the `LIC_FILES_CHKSUM` line for a single license identifier.
+///
+/// When `single_license` is true the first candidate file that exists in
+/// `crate_root` is used (the common case of one license file), otherwise the
+/// identifier itself is used to narrow down which of the candidate files
+/// backs it. The `md5` value is a fixed all-zero placeholder, not a checksum
+/// computed from the file.
+pub(crate) fn file(crate_root: &Path, rel_dir: &Path, license: &str, single_license: bool) -> String {
+    let candidate = if single_license {
+        LICENSE_FILE_CANDIDATES
+            .iter()
+            .find(|name| crate_root.join(name).exists())
+            .copied()
+    } else {
+        LICENSE_FILE_CANDIDATES
+            .iter()
+            .find(|name| name.to_lowercase().contains(&license.to_lowercase()))
+            .copied()
+            .or_else(|| {
+                LICENSE_FILE_CANDIDATES
+                    .iter()
+                    .find(|name| crate_root.join(name).exists())
+                    .copied()
+            })
+    }
+    .unwrap_or("LICENSE");
+
+    format!(
+        "file://{}/{};md5=0000000000000000000000000000000\\",
+        rel_dir.display(),
+        candidate
+    )
+}
+
+/// A parsed SPDX license expression.
+///
+/// Only the subset of the SPDX expression grammar that shows up in
+/// `Cargo.toml` files is supported: `AND`, `OR`, `WITH` and parenthesized
+/// grouping. `WITH` binds to its left-hand license identifier and never
+/// produces a standalone license entry on its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SpdxExpr {
+    /// A single license identifier, e.g. `MIT` or `Apache-2.0 WITH LLVM-exception`
+    License(String),
+    And(Box<SpdxExpr>, Box<SpdxExpr>),
+    Or(Box<SpdxExpr>, Box<SpdxExpr>),
+}
+
+impl SpdxExpr {
+    /// Parses a `license` string from `Cargo.toml` into an expression tree.
+    ///
+    /// An empty expression (no `package.license` set) falls back to
+    /// [`CLOSED_LICENSE`] rather than erroring out.
+    pub(crate) fn parse(input: &str) -> Result<SpdxExpr> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(SpdxExpr::License(CLOSED_LICENSE.to_string()));
+        }
+
+        // the deprecated `MIT/Apache-2.0` slash syntax is still what most
+        // pre-2019 crates use, and it isn't valid SPDX, so it has to be
+        // special-cased before the real SPDX tokenizer ever sees it
+        if let Some(parts) = legacy_slash_licenses(input) {
+            return Ok(parts
+                .into_iter()
+                .map(|lic| SpdxExpr::License(lic.trim().to_string()))
+                .reduce(|lhs, rhs| SpdxExpr::Or(Box::new(lhs), Box::new(rhs)))
+                .expect("split('/') always yields at least one part"));
+        }
+
+        let tokens = tokenize(input);
+        let mut parser = Parser { tokens: &tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(anyhow!(
+                "Unexpected trailing tokens in SPDX expression '{}'",
+                input
+            ));
+        }
+        Ok(expr)
+    }
+
+    /// Renders this expression using Yocto `LICENSE` syntax: `AND` becomes
+    /// `&`, `OR` becomes `|`, and nested boolean groups are parenthesized.
+    pub(crate) fn to_yocto(&self) -> String {
+        self.render(true)
+    }
+
+    fn render(&self, top: bool) -> String {
+        match self {
+            SpdxExpr::License(lic) => lic.clone(),
+            SpdxExpr::And(lhs, rhs) => {
+                let joined = format!("{} & {}", lhs.render(false), rhs.render(false));
+                if top { joined } else { format!("({joined})") }
+            }
+            SpdxExpr::Or(lhs, rhs) => {
+                let joined = format!("{} | {}", lhs.render(false), rhs.render(false));
+                if top { joined } else { format!("({joined})") }
+            }
+        }
+    }
+
+    /// The distinct license identifiers used in this expression, in the
+    /// order they first appear. A `WITH` exception stays attached to its
+    /// base identifier and counts as a single entry.
+    pub(crate) fn licenses(&self) -> Vec<String> {
+        let mut out = vec![];
+        self.collect_licenses(&mut out);
+        out.dedup();
+        out
+    }
+
+    fn collect_licenses(&self, out: &mut Vec<String>) {
+        match self {
+            SpdxExpr::License(lic) => {
+                if !out.contains(lic) {
+                    out.push(lic.clone());
+                }
+            }
+            SpdxExpr::And(lhs, rhs) | SpdxExpr::Or(lhs, rhs) => {
+                lhs.collect_licenses(out);
+                rhs.collect_licenses(out);
+            }
+        }
+    }
+
+    /// Whether this expression can be satisfied entirely within `allowed`
+    /// identifiers (an empty `allowed` list means "anything goes") while
+    /// never touching a `denied` one.
+    ///
+    /// This respects the expression's boolean structure rather than
+    /// flattening it: an `AND` requires every leaf to comply, since using
+    /// the crate means complying with every branch at once, while an `OR`
+    /// only needs a single fully-compliant branch, since the licensee can
+    /// pick which branch to exercise.
+    pub(crate) fn is_compliant(&self, allowed: &[String], denied: &[String]) -> bool {
+        match self {
+            SpdxExpr::License(lic) => {
+                !denied.contains(lic) && (allowed.is_empty() || allowed.contains(lic))
+            }
+            SpdxExpr::And(lhs, rhs) => {
+                lhs.is_compliant(allowed, denied) && rhs.is_compliant(allowed, denied)
+            }
+            SpdxExpr::Or(lhs, rhs) => {
+                lhs.is_compliant(allowed, denied) || rhs.is_compliant(allowed, denied)
+            }
+        }
+    }
+}
+
+/// Recognizes the legacy `MIT/Apache-2.0` slash syntax, which is only ever
+/// used in isolation: a `/`-joined license string that contains none of the
+/// real SPDX operators or parenthesized grouping.
+fn legacy_slash_licenses(input: &str) -> Option<Vec<&str>> {
+    if !input.contains('/') {
+        return None;
+    }
+
+    if input.contains('(')
+        || input
+            .split_whitespace()
+            .any(|tok| matches!(tok, "AND" | "OR" | "WITH"))
+    {
+        return None;
+    }
+
+    Some(input.split('/').collect())
+}
+
+/// Splits an SPDX expression into tokens: `(`, `)`, the `AND`/`OR`/`WITH`
+/// keywords, and bare license identifiers.
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+
+    let flush = |current: &mut String, tokens: &mut Vec<String>| {
+        if !current.is_empty() {
+            tokens.push(std::mem::take(current));
+        }
+    };
+
+    for c in input.chars() {
+        match c {
+            '(' | ')' => {
+                flush(&mut current, &mut tokens);
+                tokens.push(c.to_string());
+            }
+            c if c.is_whitespace() => flush(&mut current, &mut tokens),
+            c => current.push(c),
+        }
+    }
+    flush(&mut current, &mut tokens);
+
+    tokens
+}
+
+struct Parser<'t> {
+    tokens: &'t [String],
+    pos: usize,
+}
+
+impl<'t> Parser<'t> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&str> {
+        let tok = self.tokens.get(self.pos).map(String::as_str);
+        self.pos += 1;
+        tok
+    }
+
+    // or_expr := and_expr ("OR" and_expr)*
+    fn parse_or(&mut self) -> Result<SpdxExpr> {
+        let mut lhs = self.parse_and()?;
+        while self.peek() == Some("OR") {
+            self.next();
+            let rhs = self.parse_and()?;
+            lhs = SpdxExpr::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    // and_expr := primary ("AND" primary)*
+    fn parse_and(&mut self) -> Result<SpdxExpr> {
+        let mut lhs = self.parse_primary()?;
+        while self.peek() == Some("AND") {
+            self.next();
+            let rhs = self.parse_primary()?;
+            lhs = SpdxExpr::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    // primary := "(" or_expr ")" | IDENT ("WITH" IDENT)?
+    fn parse_primary(&mut self) -> Result<SpdxExpr> {
+        match self.next() {
+            Some("(") => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(")") => Ok(expr),
+                    _ => Err(anyhow!("Unbalanced parentheses in SPDX expression")),
+                }
+            }
+            Some(ident) if ident != "AND" && ident != "OR" && ident != "WITH" => {
+                let mut lic = ident.to_string();
+                if self.peek() == Some("WITH") {
+                    self.next();
+                    match self.next() {
+                        Some(exception) => lic = format!("{lic} WITH {exception}"),
+                        None => return Err(anyhow!("Expected exception identifier after WITH")),
+                    }
+                }
+                Ok(SpdxExpr::License(lic))
+            }
+            Some(other) => Err(anyhow!("Unexpected token '{}' in SPDX expression", other)),
+            None => Err(anyhow!("Unexpected end of SPDX expression")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_license_falls_back_to_closed() {
+        let expr = SpdxExpr::parse("").unwrap();
+        assert_eq!(expr, SpdxExpr::License(CLOSED_LICENSE.to_string()));
+        assert_eq!(expr.licenses(), vec![CLOSED_LICENSE.to_string()]);
+    }
+
+    #[test]
+    fn legacy_slash_syntax_becomes_or() {
+        let expr = SpdxExpr::parse("MIT/Apache-2.0").unwrap();
+        assert_eq!(expr.to_yocto(), "MIT | Apache-2.0");
+        assert_eq!(expr.licenses(), vec!["MIT".to_string(), "Apache-2.0".to_string()]);
+    }
+
+    #[test]
+    fn bare_with_exception_is_a_single_license() {
+        let expr = SpdxExpr::parse("Apache-2.0 WITH LLVM-exception").unwrap();
+        assert_eq!(
+            expr.licenses(),
+            vec!["Apache-2.0 WITH LLVM-exception".to_string()]
+        );
+    }
+
+    #[test]
+    fn or_and_and_render_with_yocto_operators() {
+        let or_expr = SpdxExpr::parse("Apache-2.0 OR MIT").unwrap();
+        assert_eq!(or_expr.to_yocto(), "Apache-2.0 | MIT");
+
+        let and_expr = SpdxExpr::parse("MIT AND BSD-3-Clause").unwrap();
+        assert_eq!(and_expr.to_yocto(), "MIT & BSD-3-Clause");
+    }
+
+    #[test]
+    fn and_requires_every_branch_to_comply() {
+        let expr = SpdxExpr::parse("Apache-2.0 AND GPL-3.0").unwrap();
+        let allowed = vec!["Apache-2.0".to_string()];
+        // GPL-3.0 isn't allowed, and AND means both branches must comply
+        assert!(!expr.is_compliant(&allowed, &[]));
+
+        let allowed_both = vec!["Apache-2.0".to_string(), "GPL-3.0".to_string()];
+        assert!(expr.is_compliant(&allowed_both, &[]));
+    }
+
+    #[test]
+    fn or_only_needs_one_compliant_branch() {
+        let expr = SpdxExpr::parse("MIT OR GPL-2.0").unwrap();
+        let denied = vec!["GPL-2.0".to_string()];
+        // the MIT branch alone satisfies policy even though GPL-2.0 is denied
+        assert!(expr.is_compliant(&[], &denied));
+
+        let denied_both = vec!["MIT".to_string(), "GPL-2.0".to_string()];
+        assert!(!expr.is_compliant(&[], &denied_both));
+    }
+
+    #[test]
+    fn parenthesized_grouping_is_preserved() {
+        let expr = SpdxExpr::parse("(MIT OR Apache-2.0) AND BSD-3-Clause").unwrap();
+        assert_eq!(expr.to_yocto(), "(MIT | Apache-2.0) & BSD-3-Clause");
+        assert_eq!(
+            expr.licenses(),
+            vec!["MIT".to_string(), "Apache-2.0".to_string(), "BSD-3-Clause".to_string()]
+        );
+    }
+}
@@ -8,6 +8,7 @@
  * except according to those terms.
  */
 
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::default::Default;
 use std::env;
 use std::fs::OpenOptions;
@@ -20,9 +21,12 @@ use cargo::{CliResult, GlobalContext};
 use clap::Parser;
 use itertools::Itertools;
 
+mod audit;
 mod git;
 mod license;
 mod package_info;
+mod template;
+mod vendor;
 
 use package_info::PackageInfo;
 
@@ -63,6 +67,40 @@ struct Args {
     #[clap(short, long)]
     /// Legacy Overrides: Use legacy override syntax
     legacy_overrides: bool,
+
+    #[clap(long, value_name = "DIR")]
+    /// Vendor all dependencies into DIR and point the recipe at them
+    /// instead of fetching each crate over the network
+    vendor: Option<PathBuf>,
+
+    #[clap(long, value_name = "FILE")]
+    /// Render the recipe from FILE instead of the built-in template
+    template: Option<PathBuf>,
+
+    #[clap(long, value_name = "SPDX,...", value_delimiter = ',')]
+    /// Fail if any dependency's license isn't one of these SPDX identifiers
+    allowed_licenses: Vec<String>,
+
+    #[clap(long, value_name = "SPDX,...", value_delimiter = ',')]
+    /// Fail if any dependency's license is one of these SPDX identifiers
+    denied_licenses: Vec<String>,
+
+    #[clap(long, alias = "all")]
+    /// Generate a recipe for every workspace member instead of just the
+    /// current package, resolving dependencies only once
+    workspace: bool,
+
+    #[clap(long)]
+    /// Run without accessing the network
+    offline: bool,
+
+    #[clap(long)]
+    /// Require that Cargo.lock is up to date
+    locked: bool,
+
+    #[clap(long)]
+    /// Require that Cargo.lock and the cache are up to date
+    frozen: bool,
 }
 
 #[derive(clap::Parser)]
@@ -88,17 +126,21 @@ fn main() {
 }
 
 fn real_main(options: Args, gctx: &mut GlobalContext) -> CliResult {
+    // --frozen is sugar for "locked + offline", matching cargo's own CLI
+    let locked = options.locked || options.frozen;
+    let offline = options.offline || options.frozen;
+
     gctx.configure(
         options.verbose as u32,
         options.quiet,
         /* color */
         None,
         /* frozen */
-        false,
+        options.frozen,
         /* locked */
-        false,
+        locked,
         /* offline */
-        false,
+        offline,
         /* target dir */
         &None,
         /* unstable flags */
@@ -107,11 +149,112 @@ fn real_main(options: Args, gctx: &mut GlobalContext) -> CliResult {
         &[],
     )?;
 
-    // Build up data about the package we are attempting to generate a recipe for
+    // Build up data about the package(s) we are attempting to generate a recipe for
     let md = PackageInfo::new(gctx, None)?;
 
-    // Our current package
-    let package = md.package()?;
+    // Resolve all dependencies once, even in --workspace mode where we emit
+    // one recipe per member, so resolution only happens a single time
+    let resolve = md.resolve()?;
+    let package_set = resolve.0;
+
+    // enforce license policy before we go any further so we never emit a
+    // recipe for a dependency tree that doesn't comply
+    if !options.allowed_licenses.is_empty() || !options.denied_licenses.is_empty() {
+        audit::check(
+            &package_set,
+            &resolve.1,
+            &options.allowed_licenses,
+            &options.denied_licenses,
+        )?;
+    }
+
+    // when vendoring, every dependency is mirrored into a single directory
+    // up front and the recipe's SRC_URI just points at it, so we skip the
+    // per-crate crate:// URI generation below entirely
+    if let Some(vendor_dir) = options.vendor.as_ref() {
+        vendor::vendor(vendor_dir, &package_set, &resolve.1)?;
+    }
+
+    // attempt to figure out the git repo for this project, shared by every
+    // recipe we write since it describes the checkout as a whole
+    let project_repo = git::ProjectRepo::new(gctx).unwrap_or_else(|e| {
+        println!("{}", e);
+        Default::default()
+    });
+
+    let template_src = match options.template.as_ref() {
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read template file '{}'", path.display()))?,
+        None => include_str!("bitbake.template").to_string(),
+    };
+
+    if options.workspace {
+        let members = md.members().cloned().collect::<Vec<_>>();
+        for package in &members {
+            write_recipe(
+                &options,
+                &md,
+                package,
+                &package_set,
+                &resolve.1,
+                &project_repo,
+                &template_src,
+            )?;
+        }
+    } else {
+        let package = md.package()?.clone();
+        write_recipe(
+            &options,
+            &md,
+            &package,
+            &package_set,
+            &resolve.1,
+            &project_repo,
+            &template_src,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Computes the transitive closure of `root`'s dependencies within `resolve`
+/// via BFS over `Resolve::deps`.
+///
+/// `resolve` covers the whole workspace's dependency graph, shared across
+/// every `--workspace` member so resolution only happens once, so without
+/// this a member's recipe would list every other member's dependencies too
+/// instead of just its own.
+fn member_closure(resolve: &Resolve, root: PackageId) -> HashSet<PackageId> {
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(root);
+
+    while let Some(pkg_id) = queue.pop_front() {
+        if !seen.insert(pkg_id) {
+            continue;
+        }
+        for (dep_id, _) in resolve.deps(pkg_id) {
+            if !seen.contains(&dep_id) {
+                queue.push_back(dep_id);
+            }
+        }
+    }
+
+    seen
+}
+
+/// Builds and writes the BitBake recipe for a single package, sharing the
+/// already-resolved dependency set and project git metadata with every
+/// other member when run in `--workspace` mode
+fn write_recipe(
+    options: &Args,
+    md: &PackageInfo,
+    package: &Package,
+    package_set: &PackageSet,
+    resolve: &Resolve,
+    project_repo: &git::ProjectRepo,
+    template_src: &str,
+) -> CliResult {
     let crate_root = package
         .manifest_path()
         .parent()
@@ -121,20 +264,47 @@ fn real_main(options: Args, gctx: &mut GlobalContext) -> CliResult {
         println!("Package name contains an underscore");
     }
 
-    // Resolve all dependencies (generate or use Cargo.lock as necessary)
-    let resolve = md.resolve()?;
-    let package_set = resolve.0;
-
-    // build the crate URIs
+    // build the crate URIs. The sibling-path wiring for --workspace mode
+    // always has to run, vendored or not, since vendor() never vendors path
+    // dependencies; only the per-crate registry/git URI generation is
+    // replaced wholesale by the single vendored file:// entry below.
+    //
+    // `resolve` is the single whole-workspace resolve shared by every
+    // member, so it has to be narrowed to this member's own transitive
+    // closure here, otherwise every member's recipe would list every other
+    // member's dependencies too.
+    let closure = member_closure(resolve, package.package_id());
     let mut src_uri_extras = vec![];
     let mut src_uris = resolve
-        .1
         .iter()
+        .filter(|pkg| closure.contains(pkg))
         .filter_map(|pkg| {
             // get the source info for this package
             let src_id = pkg.source_id();
             if pkg.name() == package.name() {
                 None
+            } else if src_id.is_path() {
+                // path dependencies either live inside the crate we are
+                // packaging (nothing to do), or in --workspace mode
+                // point at a sibling member recipe, which we need to
+                // tell Cargo where to find via EXTRA_OECARGO_PATHS
+                // rather than a crate:// URI
+                if options.workspace {
+                    if let Ok(sibling) = package_set.get_one(pkg) {
+                        if let Ok(sibling_rel_dir) = md.rel_dir_for(sibling.manifest_path()) {
+                            src_uri_extras.push(format!(
+                                "EXTRA_OECARGO_PATHS += \"${{WORKDIR}}/git/{}\"",
+                                sibling_rel_dir.display()
+                            ));
+                        }
+                    }
+                }
+                None
+            } else if options.vendor.is_some() {
+                // vendored separately into a single file:// entry below, so
+                // no per-crate URI or SRCREV/EXTRA_OECARGO_PATHS bookkeeping
+                // is needed here
+                None
             } else if src_id.is_registry() {
                 // this package appears in a crate registry
                 if options.no_checksums {
@@ -150,14 +320,9 @@ fn real_main(options: Args, gctx: &mut GlobalContext) -> CliResult {
                         CRATES_IO_URL,
                         pkg.name(),
                         pkg.version(),
-                        get_checksum(&package_set, pkg)
+                        get_checksum(package_set, pkg)
                     ))
                 }
-            } else if src_id.is_path() {
-                // we don't want to spit out path based
-                // entries since they're within the crate
-                // we are packaging
-                None
             } else if src_id.is_git() {
                 // Just use the default download method for git repositories
                 // found in the source URIs, since cargo currently cannot
@@ -223,6 +388,15 @@ fn real_main(options: Args, gctx: &mut GlobalContext) -> CliResult {
     // sort the crate list
     src_uris.sort();
 
+    // when vendoring, point SRC_URI at the vendor tree instead, relative to
+    // the recipe's own directory rather than the absolute `--vendor` path
+    // the operator happened to pass, so the recipe stays reproducible when
+    // checked in and built somewhere else
+    if let Some(vendor_dir) = options.vendor.as_ref() {
+        let vendor_uri = vendor::recipe_relative_uri(vendor_dir);
+        src_uris.insert(0, format!("    file://{} \\\n", vendor_uri.display()));
+    }
+
     // root package metadata
     let metadata = package.manifest().metadata();
 
@@ -251,30 +425,35 @@ fn real_main(options: Args, gctx: &mut GlobalContext) -> CliResult {
         )?
         .trim();
 
-    // package license
-    let license = metadata.license.as_ref().map_or_else(
-        || {
+    // package license, parsed as an SPDX expression so that modern
+    // `Apache-2.0 OR MIT`-style expressions are understood, not just the
+    // deprecated `MIT/Apache-2.0` slash syntax
+    let license_expr = match metadata.license.as_ref() {
+        Some(spdx) => license::SpdxExpr::parse(spdx)
+            .with_context(|| format!("Unable to parse SPDX license expression '{}'", spdx))?,
+        None => {
             println!("No package.license set in your Cargo.toml, trying package.license_file");
-            metadata.license_file.as_ref().map_or_else(
+            let atom = metadata.license_file.as_ref().map_or_else(
                 || {
                     println!("No package.license_file set in your Cargo.toml");
                     println!("Assuming {} license", license::CLOSED_LICENSE);
-                    license::CLOSED_LICENSE
+                    license::CLOSED_LICENSE.to_string()
                 },
-                String::as_str,
-            )
-        },
-        String::as_str,
-    );
+                String::clone,
+            );
+            license::SpdxExpr::License(atom)
+        }
+    };
 
     // compute the relative directory into the repo our Cargo.toml is at
-    let rel_dir = md.rel_dir()?;
+    let rel_dir = md.rel_dir_for(package.manifest_path())?;
 
-    // license files for the package
-    let mut lic_files = vec![];
-    let licenses: Vec<&str> = license.split('/').collect();
+    // license files for the package, one LIC_FILES_CHKSUM entry per unique
+    // license identifier found in the expression
+    let licenses = license_expr.licenses();
     let single_license = licenses.len() == 1;
-    for lic in licenses {
+    let mut lic_files = vec![];
+    for lic in &licenses {
         lic_files.push(format!(
             "    {}",
             license::file(crate_root, &rel_dir, lic, single_license)
@@ -282,13 +461,7 @@ fn real_main(options: Args, gctx: &mut GlobalContext) -> CliResult {
     }
 
     // license data in Yocto fmt
-    let license = license.split('/').map(str::trim).join(" | ");
-
-    // attempt to figure out the git repo for this project
-    let project_repo = git::ProjectRepo::new(gctx).unwrap_or_else(|e| {
-        println!("{}", e);
-        Default::default()
-    });
+    let license = license_expr.to_yocto();
 
     // if this is not a tag we need to include some data about the version in PV so that
     // the sstate cache remains valid
@@ -322,25 +495,29 @@ fn real_main(options: Args, gctx: &mut GlobalContext) -> CliResult {
         // CliResult accepts only failure::Error, not failure::Context
         .map_err(|e| anyhow!("Unable to open bitbake recipe file with: {}", e))?;
 
+    // fields available to the recipe template, either the embedded default
+    // or a user-supplied one loaded via --template
+    let fields = BTreeMap::from([
+        ("name", package.name().to_string()),
+        ("version", package.version().to_string()),
+        ("summary", summary.to_string()),
+        ("homepage", homepage.to_string()),
+        ("license", license),
+        ("lic_files", lic_files.join("\n")),
+        ("src_uri", src_uris.join("")),
+        ("src_uri_extras", src_uri_extras.join("\n")),
+        ("project_rel_dir", rel_dir.display().to_string()),
+        ("project_src_uri", project_repo.uri.clone()),
+        ("project_src_rev", project_repo.rev.clone()),
+        ("git_srcpv", git_srcpv),
+        ("cargo_bitbake_ver", env!("CARGO_PKG_VERSION").to_string()),
+    ]);
+
+    let rendered = template::render(template_src, &fields)?;
+
     // write the contents out
-    write!(
-        file,
-        include_str!("bitbake.template"),
-        name = package.name(),
-        version = package.version(),
-        summary = summary,
-        homepage = homepage,
-        license = license,
-        lic_files = lic_files.join(""),
-        src_uri = src_uris.join(""),
-        src_uri_extras = src_uri_extras.join("\n"),
-        project_rel_dir = rel_dir.display(),
-        project_src_uri = project_repo.uri,
-        project_src_rev = project_repo.rev,
-        git_srcpv = git_srcpv,
-        cargo_bitbake_ver = env!("CARGO_PKG_VERSION"),
-    )
-    .map_err(|e| anyhow!("Unable to write to bitbake recipe file with: {}", e))?;
+    file.write_all(rendered.as_bytes())
+        .map_err(|e| anyhow!("Unable to write to bitbake recipe file with: {}", e))?;
 
     println!("Wrote: {}", recipe_path.display());
 
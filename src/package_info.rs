@@ -10,7 +10,6 @@ use cargo::{ops, CargoResult, GlobalContext};
 /// Represents the package we are trying to generate a recipe for
 pub(crate) struct PackageInfo<'gctx> {
     pub(crate) _gctx: &'gctx GlobalContext,
-    pub(crate) current_manifest: PathBuf,
     pub(crate) ws: Workspace<'gctx>,
 }
 
@@ -21,11 +20,7 @@ impl<'gctx> PackageInfo<'gctx> {
         let manifest_path = manifest_path.map_or_else(|| gctx.cwd().to_path_buf(), PathBuf::from);
         let root = important_paths::find_root_manifest_for_wd(&manifest_path)?;
         let ws = Workspace::new(&root, gctx)?;
-        Ok(PackageInfo {
-            _gctx: gctx,
-            current_manifest: root,
-            ws,
-        })
+        Ok(PackageInfo { _gctx: gctx, ws })
     }
 
     /// provides the current package we are working with
@@ -33,6 +28,12 @@ impl<'gctx> PackageInfo<'gctx> {
         self.ws.current()
     }
 
+    /// every package that's a member of this workspace, for `--workspace`
+    /// mode where we emit one recipe per member
+    pub(crate) fn members(&self) -> impl Iterator<Item = &Package> {
+        self.ws.members()
+    }
+
     /// Generates a package registry by using the Cargo.lock or
     /// creating one as necessary
     pub(crate) fn registry(&self) -> CargoResult<PackageRegistry<'gctx>> {
@@ -43,7 +44,20 @@ impl<'gctx> PackageInfo<'gctx> {
     }
 
     /// Resolve the packages necessary for the workspace
+    ///
+    /// In `--locked`/`--frozen` mode this refuses to regenerate `Cargo.lock`:
+    /// the lock file must already exist and be up to date, matching Cargo's
+    /// own semantics, so the `SRC_URI`/checksums we emit exactly match what's
+    /// committed.
     pub(crate) fn resolve(&self) -> CargoResult<(PackageSet<'gctx>, Resolve)> {
+        if self._gctx.locked() && !self.ws.root().join("Cargo.lock").exists() {
+            return Err(anyhow!(
+                "the --locked/--frozen flag requires a Cargo.lock file, \
+                 but none exists at '{}'",
+                self.ws.root().join("Cargo.lock").display()
+            ));
+        }
+
         // build up our registry
         let mut registry = self.registry()?;
 
@@ -73,15 +87,15 @@ impl<'gctx> PackageInfo<'gctx> {
 
     /// packages that are part of a workspace are a sub directory from the
     /// top level which we need to record, this provides us with that
-    /// relative directory
-    pub(crate) fn rel_dir(&self) -> CargoResult<PathBuf> {
+    /// relative directory for the given member's manifest
+    pub(crate) fn rel_dir_for(&self, manifest_path: &Path) -> CargoResult<PathBuf> {
         // this is the top level of the workspace
         let root = self.ws.root().to_path_buf();
-        // path where our current package's Cargo.toml lives
-        let cwd = self.current_manifest.parent().ok_or_else(|| {
+        // path where the package's Cargo.toml lives
+        let cwd = manifest_path.parent().ok_or_else(|| {
             anyhow!(
                 "Could not get parent of directory '{}'",
-                self.current_manifest.display()
+                manifest_path.display()
             )
         })?;
 
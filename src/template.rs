@@ -0,0 +1,71 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+
+/// Renders `template`, replacing every `{{ name }}` placeholder with its
+/// value from `fields`.
+///
+/// This exists instead of `format!`/`write!` because `--template` loads a
+/// template file at runtime, and named arguments to `format!` must be known
+/// at compile time. Unknown placeholders are a hard error rather than being
+/// left untouched, so a typo in a downstream layer's template is caught
+/// immediately instead of silently producing a broken recipe.
+pub(crate) fn render(template: &str, fields: &BTreeMap<&str, String>) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after_open = &rest[start + 2..];
+
+        let end = after_open
+            .find("}}")
+            .ok_or_else(|| anyhow!("Unterminated '{{{{' placeholder in template"))?;
+
+        let name = after_open[..end].trim();
+        let value = fields
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown template placeholder '{{{{ {name} }}}}'"))?;
+        out.push_str(value);
+
+        rest = &after_open[end + 2..];
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_every_placeholder() {
+        let mut fields = BTreeMap::new();
+        fields.insert("name", "foo".to_string());
+        fields.insert("version", "1.0".to_string());
+
+        let out = render("{{ name }}-{{version}}.bb", &fields).unwrap();
+        assert_eq!(out, "foo-1.0.bb");
+    }
+
+    #[test]
+    fn unknown_placeholder_is_an_error() {
+        let fields = BTreeMap::new();
+        let err = render("{{ missing }}", &fields).unwrap_err();
+        assert!(err.to_string().contains("missing"));
+    }
+
+    #[test]
+    fn unterminated_placeholder_is_an_error() {
+        let fields = BTreeMap::new();
+        let err = render("{{ name", &fields).unwrap_err();
+        assert!(err.to_string().contains("Unterminated"));
+    }
+
+    #[test]
+    fn template_with_no_placeholders_is_unchanged() {
+        let fields = BTreeMap::new();
+        assert_eq!(render("plain text", &fields).unwrap(), "plain text");
+    }
+}
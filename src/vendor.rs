@@ -0,0 +1,248 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use cargo::core::{PackageSet, Resolve};
+use cargo::CargoResult;
+use cargo_util::Sha256;
+
+/// Vendors every resolved, non-path dependency into `dir`, producing a
+/// self-contained offline mirror plus the `.cargo/config.toml` needed to
+/// build against it without network access.
+///
+/// Returns nothing on success; callers point the generated recipe's
+/// `SRC_URI` at `dir` themselves (see [`recipe_relative_uri`]).
+pub(crate) fn vendor(dir: &Path, package_set: &PackageSet, resolve: &Resolve) -> CargoResult<()> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Unable to create vendor directory '{}'", dir.display()))?;
+
+    // every git URL vendored below needs its own `[source."<url>"]` stanza in
+    // `.cargo/config.toml` so Cargo redirects that clone to the vendored copy
+    // too, not just crates-io
+    let mut git_sources = BTreeSet::new();
+
+    for pkg_id in resolve.iter() {
+        let src_id = pkg_id.source_id();
+        if src_id.is_path() {
+            // path dependencies already live in the workspace, nothing to vendor
+            continue;
+        }
+
+        let pkg = package_set.get_one(pkg_id)?;
+        let dest = dir.join(format!("{}-{}", pkg_id.name(), pkg_id.version()));
+        copy_dir(pkg.root(), &dest)?;
+
+        // Cargo's directory-source verification hashes every file and
+        // compares the whole tree against this `package` checksum, the same
+        // one recorded in Cargo.lock, so registry crates get the real
+        // registry checksum. git dependencies are snapshotted at their
+        // resolved revision by virtue of `pkg.root()` already pointing at
+        // that checkout and have no such checksum, so theirs is `null`
+        let checksum = checksum_dir(&dest, pkg.summary().checksum())?;
+        fs::write(dest.join(".cargo-checksum.json"), checksum).with_context(|| {
+            format!("Unable to write checksum file for '{}'", dest.display())
+        })?;
+
+        if src_id.is_git() {
+            git_sources.insert(src_id.url().to_string());
+        }
+    }
+
+    write_cargo_config(dir, &git_sources)?;
+
+    Ok(())
+}
+
+/// Computes the path to put in the recipe's `SRC_URI` for a vendored
+/// directory, relative to the directory the recipe itself is written into
+/// rather than `dir`'s absolute path.
+///
+/// A recipe with an absolute `file://` path only builds on the machine that
+/// generated it, defeating the whole point of vendoring for a reproducible,
+/// checked-in recipe. When `dir` isn't under the current directory (so no
+/// relative path can be formed) this falls back to `dir`'s last path
+/// component and warns, since the vendor tree then needs to be placed next
+/// to the recipe by hand.
+pub(crate) fn recipe_relative_uri(dir: &Path) -> PathBuf {
+    let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    // a relative `dir` is taken as relative to the current directory
+    let abs_dir = if dir.is_absolute() { dir.to_path_buf() } else { cwd.join(dir) };
+
+    match abs_dir.strip_prefix(&cwd) {
+        Ok(rel) => rel.to_path_buf(),
+        Err(_) => {
+            println!(
+                "Warning: --vendor directory '{}' is not under the current directory; \
+                 the recipe will reference it by name only, so place the vendor tree \
+                 next to the recipe for a reproducible SRC_URI",
+                dir.display()
+            );
+            PathBuf::from(dir.file_name().unwrap_or_default())
+        }
+    }
+}
+
+/// Recursively copies `src` into `dest`, creating directories as needed
+fn copy_dir(src: &Path, dest: &Path) -> CargoResult<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dest.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Computes a `cargo vendor`-style `.cargo-checksum.json` covering every
+/// file in `dir`, keyed by path relative to `dir`, with `package` set to
+/// `package_checksum` (the crate's registry sha256, same as `Cargo.lock`
+/// records) so Cargo's directory-source verification validates, or `null`
+/// when the source has no such checksum (e.g. a git dependency)
+fn checksum_dir(dir: &Path, package_checksum: Option<&str>) -> CargoResult<String> {
+    let mut entries = vec![];
+    collect_checksums(dir, dir, &mut entries)?;
+    entries.sort();
+
+    let files = entries
+        .iter()
+        .map(|(path, hash)| format!("\"{path}\":\"{hash}\""))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let package = package_checksum.map_or_else(|| "null".to_string(), |c| format!("\"{c}\""));
+
+    Ok(format!("{{\"files\":{{{files}}},\"package\":{package}}}"))
+}
+
+fn collect_checksums(root: &Path, dir: &Path, out: &mut Vec<(String, String)>) -> CargoResult<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_checksums(root, &path, out)?;
+        } else {
+            let mut hasher = Sha256::new();
+            hasher.update(&fs::read(&path)?);
+            let rel = path
+                .strip_prefix(root)
+                .expect("walked path under root")
+                .display()
+                .to_string();
+            out.push((rel, hasher.finish_hex()));
+        }
+    }
+    Ok(())
+}
+
+/// Writes the `.cargo/config.toml` that redirects `crates-io`, plus every
+/// vendored git URL in `git_sources`, to the vendored directory.
+///
+/// Without a `[source."<url>"]` stanza per git URL, Cargo has no way to know
+/// a git dependency's vendored copy should replace the network clone, so the
+/// build would still reach out over the network for every git dependency
+/// even though it's already sitting in `dir`.
+///
+/// The `directory` is written relative to `config.toml`'s own location
+/// (`<dir>/.cargo/config.toml` pointing at `<dir>`, i.e. `".."`) rather than
+/// as the absolute `--vendor` path the operator happened to pass, so the
+/// vendor tree stays self-contained and portable to wherever it's unpacked
+/// on the build machine.
+fn write_cargo_config(dir: &Path, git_sources: &BTreeSet<String>) -> CargoResult<()> {
+    let cargo_dir = dir.join(".cargo");
+    fs::create_dir_all(&cargo_dir)?;
+
+    let mut config = String::from(
+        "[source.crates-io]\nreplace-with = \"vendored-sources\"\n\n\
+         [source.vendored-sources]\ndirectory = \"..\"\n",
+    );
+
+    for url in git_sources {
+        config.push_str(&format!(
+            "\n[source.\"{url}\"]\ngit = \"{url}\"\nreplace-with = \"vendored-sources\"\n"
+        ));
+    }
+
+    fs::write(cargo_dir.join("config.toml"), config)
+        .context("Unable to write .cargo/config.toml for vendored sources")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "cargo-bitbake-vendor-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn recipe_relative_uri_strips_the_cwd_prefix() {
+        let cwd = env::current_dir().unwrap();
+        let vendor_dir = cwd.join("vendor");
+        assert_eq!(recipe_relative_uri(&vendor_dir), Path::new("vendor"));
+    }
+
+    #[test]
+    fn cargo_config_directory_is_relative() {
+        let dir = scratch_dir("config");
+        write_cargo_config(&dir, &BTreeSet::new()).unwrap();
+
+        let config = fs::read_to_string(dir.join(".cargo/config.toml")).unwrap();
+        assert!(config.contains("directory = \"..\""));
+        assert!(!config.contains(dir.to_string_lossy().as_ref()));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cargo_config_redirects_every_git_source() {
+        let dir = scratch_dir("config-git");
+        let git_sources = BTreeSet::from(["https://example.com/foo.git".to_string()]);
+        write_cargo_config(&dir, &git_sources).unwrap();
+
+        let config = fs::read_to_string(dir.join(".cargo/config.toml")).unwrap();
+        assert!(config.contains("[source.\"https://example.com/foo.git\"]"));
+        assert!(config.contains("git = \"https://example.com/foo.git\""));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checksum_dir_hashes_every_file() {
+        let dir = scratch_dir("checksum");
+        fs::write(dir.join("lib.rs"), b"fn main() {}").unwrap();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/mod.rs"), b"mod foo;").unwrap();
+
+        let checksum = checksum_dir(&dir, Some("deadbeef")).unwrap();
+        assert!(checksum.contains("\"lib.rs\""));
+        assert!(checksum.contains("\"src/mod.rs\""));
+        assert!(checksum.contains("\"package\":\"deadbeef\""));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checksum_dir_without_package_checksum_is_null() {
+        let dir = scratch_dir("checksum-null");
+        fs::write(dir.join("lib.rs"), b"fn main() {}").unwrap();
+
+        let checksum = checksum_dir(&dir, None).unwrap();
+        assert!(checksum.contains("\"package\":null"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}